@@ -0,0 +1,145 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Secret and routing info for an AWS access key id.
+#[derive(Debug, Clone)]
+pub struct Credential {
+    pub secret_key: String,
+    pub backend_url: String,
+    pub allowed_regions: Vec<String>,
+    pub allowed_services: Vec<String>,
+    /// When true, buffer the request body and verify its SHA-256 against
+    /// `x-amz-content-sha256` before forwarding it.
+    pub verify_payload: bool,
+    /// Region/service to re-sign for, if different from the client's.
+    /// `None` means "same as the client's credential scope".
+    pub backend_region: Option<String>,
+    pub backend_service: Option<String>,
+}
+
+/// Looks up the secret key and backend routing for an access key id.
+#[async_trait::async_trait]
+pub trait CredentialStore: Send + Sync {
+    async fn lookup(&self, access_key_id: &str) -> Option<Credential>;
+}
+
+#[derive(Deserialize)]
+struct CredentialConfigEntry {
+    access_key_id: String,
+    secret_key: String,
+    backend_url: String,
+    #[serde(default)]
+    allowed_regions: Vec<String>,
+    #[serde(default)]
+    allowed_services: Vec<String>,
+    #[serde(default)]
+    verify_payload: bool,
+    #[serde(default)]
+    backend_region: Option<String>,
+    #[serde(default)]
+    backend_service: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CredentialConfigFile {
+    credentials: Vec<CredentialConfigEntry>,
+}
+
+/// A `CredentialStore` backed by a `HashMap` seeded from a JSON config file.
+pub struct InMemoryCredentialStore {
+    credentials: HashMap<String, Credential>,
+}
+
+impl InMemoryCredentialStore {
+    pub fn from_config_file(path: &str) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)?;
+        let config: CredentialConfigFile = serde_json::from_str(&raw)?;
+        let credentials = config
+            .credentials
+            .into_iter()
+            .map(|entry| {
+                (
+                    entry.access_key_id,
+                    Credential {
+                        secret_key: entry.secret_key,
+                        backend_url: entry.backend_url,
+                        allowed_regions: entry.allowed_regions,
+                        allowed_services: entry.allowed_services,
+                        verify_payload: entry.verify_payload,
+                        backend_region: entry.backend_region,
+                        backend_service: entry.backend_service,
+                    },
+                )
+            })
+            .collect();
+        Ok(Self { credentials })
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialStore for InMemoryCredentialStore {
+    async fn lookup(&self, access_key_id: &str) -> Option<Credential> {
+        self.credentials.get(access_key_id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn from_config_file_loads_and_looks_up_credentials() {
+        let path = std::env::temp_dir().join(format!(
+            "iam-the-service-test-credentials-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"{
+                "credentials": [
+                    {
+                        "access_key_id": "AKIDEXAMPLE",
+                        "secret_key": "secret",
+                        "backend_url": "https://backend.example.com",
+                        "allowed_regions": ["us-east-1"],
+                        "allowed_services": ["s3"],
+                        "verify_payload": true,
+                        "backend_region": "us-west-2",
+                        "backend_service": "s3"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let store = InMemoryCredentialStore::from_config_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let credential = store.lookup("AKIDEXAMPLE").await.unwrap();
+        assert_eq!(credential.backend_url, "https://backend.example.com");
+        assert_eq!(credential.allowed_regions, vec!["us-east-1".to_string()]);
+        assert!(credential.verify_payload);
+        assert_eq!(credential.backend_region.as_deref(), Some("us-west-2"));
+
+        assert!(store.lookup("unknown-key").await.is_none());
+    }
+
+    #[test]
+    fn config_entry_defaults_optional_fields() {
+        let raw = r#"{
+            "credentials": [
+                {
+                    "access_key_id": "AKIDEXAMPLE",
+                    "secret_key": "secret",
+                    "backend_url": "https://backend.example.com"
+                }
+            ]
+        }"#;
+        let config: CredentialConfigFile = serde_json::from_str(raw).unwrap();
+        let entry = &config.credentials[0];
+        assert!(entry.allowed_regions.is_empty());
+        assert!(entry.allowed_services.is_empty());
+        assert!(!entry.verify_payload);
+        assert_eq!(entry.backend_region, None);
+    }
+}
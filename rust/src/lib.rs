@@ -13,20 +13,46 @@ use axum::{
 use tower::{buffer::BufferLayer, BoxError, ServiceBuilder};
 use tracing::{error, info};
 
+mod credential_store;
 mod rate_limiter;
 mod sigv4;
+use credential_store::{CredentialStore, InMemoryCredentialStore};
 use rate_limiter::{ip_rate_limiter, RateLimiter};
 
+/// Hard cap on how lenient `max_clock_skew` can be.
+const MAX_CLOCK_SKEW_CAP: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(15 * 60);
+
+/// Most a request body can be buffered to when `verify_payload` is enabled.
+pub(crate) const MAX_BODY_BYTES: usize = 1_000_000;
+
 #[derive(Clone)]
 struct AppState {
     rate_limiter: Arc<RateLimiter>,
     client: reqwest::Client,
+    credential_store: Arc<dyn CredentialStore>,
+    max_clock_skew: Duration,
 }
 
-pub async fn start(http_addr: &str) {
+pub async fn start(
+    http_addr: &str,
+    credentials_config_path: &str,
+    max_clock_skew_secs: Option<u64>,
+) {
+    let credential_store: Arc<dyn CredentialStore> = Arc::new(
+        InMemoryCredentialStore::from_config_file(credentials_config_path)
+            .expect("failed to load credential store config"),
+    );
+    let max_clock_skew = max_clock_skew_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_MAX_CLOCK_SKEW)
+        .min(MAX_CLOCK_SKEW_CAP);
+
     let state = AppState {
         rate_limiter: Arc::new(RateLimiter::new(10, Duration::from_secs(60))), // 10 requests per minute
         client: reqwest::Client::new(),
+        credential_store,
+        max_clock_skew,
     };
 
     let app = axum::Router::new()
@@ -47,7 +73,7 @@ pub async fn start(http_addr: &str) {
                     )
                 }))
                 .layer(BufferLayer::new(1024))
-                .layer(DefaultBodyLimit::max(1_000_000))
+                .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
                 // also see https://docs.rs/tower-http/0.6.1/tower_http/request_id/index.html#example
                 .layer(tower::timeout::TimeoutLayer::new(Duration::from_secs(60))) // 30 second timeout
                 .layer(middleware::from_fn_with_state(
@@ -76,6 +102,13 @@ pub enum AppError {
     CustomCode(anyhow::Error, axum::http::StatusCode),
     RateLimited(anyhow::Error),
     ValidationError(validator::ValidationErrors),
+    /// An authenticated-but-rejected request (bad/missing/expired SigV4
+    /// signature, disallowed region or service, ...). Always maps to 403 so
+    /// we never leak *why* via the status code alone.
+    Forbidden(String),
+    /// `X-Amz-Date` is outside the allowed clock-skew window, or doesn't
+    /// match the credential scope date.
+    RequestTimeTooSkewed(String),
 }
 
 // Tell axum how to convert `AppError` into a response.
@@ -94,6 +127,8 @@ impl IntoResponse for AppError {
             AppError::ValidationError(e) => {
                 (StatusCode::BAD_REQUEST, format!("Validation error: {}", e))
             }
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::RequestTimeTooSkewed(msg) => (StatusCode::FORBIDDEN, msg),
         }
         .into_response()
     }
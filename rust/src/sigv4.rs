@@ -1,19 +1,28 @@
 use anyhow::Error;
 use axum::{
-    body::Body,
+    body::{Body, Bytes},
     extract::State,
     http::{Request, Response, StatusCode},
     response::IntoResponse,
 };
-use futures::TryStreamExt;
+use chrono::Utc;
+use futures::{Stream, TryStreamExt};
 use hex;
 use hmac::{Hmac, Mac};
-use regex;
 use reqwest;
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fmt::Write;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+use subtle::ConstantTimeEq;
 
-use crate::AppState;
+use crate::{AppError, AppState};
+
+const STREAMING_PAYLOAD_SHA: &str = "STREAMING-AWS4-HMAC-SHA256-PAYLOAD";
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -31,25 +40,19 @@ fn get_sha256(data: &[u8]) -> Vec<u8> {
 }
 
 fn get_string_to_sign(
-    req: &Request<Body>,
+    amz_date: &str,
     canonical_request: &str,
     service: &str,
     region: &str,
 ) -> String {
     let mut s = String::from("AWS4-HMAC-SHA256\n");
 
-    let x_amz_date = req
-        .headers()
-        .get("X-Amz-Date")
-        .and_then(|value| value.to_str().ok())
-        .unwrap_or_default();
-
-    s.push_str(x_amz_date);
+    s.push_str(amz_date);
     s.push('\n');
 
     let scope = format!(
         "{}/{}/{}/{}",
-        &x_amz_date[..8],
+        &amz_date[..8],
         region,
         service,
         "aws4_request"
@@ -67,14 +70,8 @@ fn get_string_to_sign(
     s
 }
 
-fn get_signing_key(req: &Request<Body>, key_secret: &str, region: &str, service: &str) -> Vec<u8> {
-    let x_amz_date = req
-        .headers()
-        .get("X-Amz-Date")
-        .and_then(|value| value.to_str().ok())
-        .unwrap_or_default();
-
-    let date_key = get_hmac(key_secret.as_bytes(), &x_amz_date[..8].as_bytes());
+fn get_signing_key(amz_date: &str, key_secret: &str, region: &str, service: &str) -> Vec<u8> {
+    let date_key = get_hmac(key_secret.as_bytes(), &amz_date[..8].as_bytes());
     let date_region_key = get_hmac(&date_key, region.as_bytes());
     let date_region_service_key = get_hmac(&date_region_key, service.as_bytes());
     let signing_key = get_hmac(&date_region_service_key, b"aws4_request");
@@ -89,13 +86,47 @@ pub struct AWSAuthHeaderCredential {
     request: String,
 }
 
+/// Where the SigV4 authorization parameters came from: the `Authorization`
+/// header (the normal case) or the query string (presigned URLs).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum AuthSource {
+    Header,
+    Query,
+}
+
 pub struct AWSAuthHeader {
     credential: AWSAuthHeaderCredential,
     signed_headers: Vec<String>,
     signature: String,
+    source: AuthSource,
+    amz_date: String,
+    /// Only set for `AuthSource::Query`: the `X-Amz-Expires` value, used to
+    /// reject presigned URLs after they've expired.
+    expires_secs: Option<u64>,
+}
+
+/// Parses `Credential=keyid/date/region/service/aws4_request` (with the
+/// `Credential=` prefix already stripped) as found in both the `Authorization`
+/// header and the `X-Amz-Credential` query parameter.
+fn parse_credential_scope(value: &str) -> Option<AWSAuthHeaderCredential> {
+    let parts: Vec<&str> = value.split('/').collect();
+    if parts.len() < 5 {
+        return None;
+    }
+    Some(AWSAuthHeaderCredential {
+        key_id: parts[0].to_string(),
+        date: parts[1].to_string(),
+        region: parts[2].to_string(),
+        service: parts[3].to_string(),
+        request: parts[4].to_string(),
+    })
 }
 
-fn get_aws_auth_header(req: &Request<Body>) -> Result<AWSAuthHeader, Error> {
+fn get_aws_auth_header(req: &Request<Body>) -> Result<Option<AWSAuthHeader>, Error> {
+    let Some(header_value) = req.headers().get("Authorization") else {
+        return Ok(None);
+    };
+
     let mut auth_header = AWSAuthHeader {
         signature: String::new(),
         credential: AWSAuthHeaderCredential {
@@ -106,42 +137,194 @@ fn get_aws_auth_header(req: &Request<Body>) -> Result<AWSAuthHeader, Error> {
             service: String::new(),
         },
         signed_headers: Vec::new(),
+        source: AuthSource::Header,
+        amz_date: req
+            .headers()
+            .get("X-Amz-Date")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+        expires_secs: None,
     };
 
     // TODO: make this more efficient, optional host override
     // Extract signed headers and other parts from the Authorization header.
-    if let Some(header_value) = req.headers().get("Authorization") {
-        let header_str = header_value
-            .to_str()
-            .expect("failed to parse auth header to string");
-        for item in header_str.split_whitespace() {
-            let item = item.trim_end_matches(",");
-            if item.starts_with("SignedHeaders=") {
-                let headers = item.trim_start_matches("SignedHeaders=").replace(",", ";");
-                auth_header.signed_headers = headers.split(';').map(str::to_string).collect();
+    let header_str = header_value
+        .to_str()
+        .expect("failed to parse auth header to string");
+    for item in header_str.split_whitespace() {
+        let item = item.trim_end_matches(",");
+        if item.starts_with("SignedHeaders=") {
+            let headers = item.trim_start_matches("SignedHeaders=").replace(",", ";");
+            auth_header.signed_headers = headers.split(';').map(str::to_string).collect();
+        }
+        if item.starts_with("Credential=") {
+            if let Some(credential) =
+                parse_credential_scope(item.trim_start_matches("Credential="))
+            {
+                auth_header.credential = credential;
             }
-            if item.starts_with("Credential=") {
-                let credential_parts: Vec<String> = item
-                    .trim_start_matches("Credential=")
-                    .split('/')
-                    .map(str::to_string)
-                    .collect();
-                if credential_parts.len() >= 5 {
-                    auth_header.credential = AWSAuthHeaderCredential {
-                        key_id: credential_parts[0].clone(),
-                        date: credential_parts[1].clone(),
-                        region: credential_parts[2].clone(),
-                        service: credential_parts[3].clone(),
-                        request: credential_parts[4].clone(),
-                    };
-                }
+        }
+        if item.starts_with("Signature=") {
+            auth_header.signature = item.trim_start_matches("Signature=").to_string();
+        }
+    }
+    Ok(Some(auth_header))
+}
+
+/// Parses a presigned-URL (query-string) SigV4 request, i.e. one with no
+/// `Authorization` header but `X-Amz-Algorithm=AWS4-HMAC-SHA256` in the query
+/// string, mirroring the split AWS (and Garage) make between header and query
+/// authorization.
+fn get_aws_auth_query(req: &Request<Body>) -> Result<Option<AWSAuthHeader>, Error> {
+    let query = req.uri().query().unwrap_or_default();
+    let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+
+    if params.get("X-Amz-Algorithm").map(String::as_str) != Some("AWS4-HMAC-SHA256") {
+        return Ok(None);
+    }
+
+    let credential = params
+        .get("X-Amz-Credential")
+        .and_then(|c| parse_credential_scope(c))
+        .ok_or_else(|| Error::msg("missing or malformed X-Amz-Credential"))?;
+
+    let signed_headers = params
+        .get("X-Amz-SignedHeaders")
+        .ok_or_else(|| Error::msg("missing X-Amz-SignedHeaders"))?
+        .split(';')
+        .map(str::to_string)
+        .collect();
+
+    let amz_date = params
+        .get("X-Amz-Date")
+        .ok_or_else(|| Error::msg("missing X-Amz-Date"))?
+        .clone();
+
+    let expires_secs = params
+        .get("X-Amz-Expires")
+        .ok_or_else(|| Error::msg("missing X-Amz-Expires"))?
+        .parse::<u64>()
+        .map_err(|_| Error::msg("invalid X-Amz-Expires"))?;
+
+    let signature = params
+        .get("X-Amz-Signature")
+        .ok_or_else(|| Error::msg("missing X-Amz-Signature"))?
+        .clone();
+
+    Ok(Some(AWSAuthHeader {
+        credential,
+        signed_headers,
+        signature,
+        source: AuthSource::Query,
+        amz_date,
+        expires_secs: Some(expires_secs),
+    }))
+}
+
+/// Parses the SigV4 authorization for a request, dispatching between the
+/// `Authorization` header and presigned query-string forms.
+fn parse_auth(req: &Request<Body>) -> Result<AWSAuthHeader, Error> {
+    if let Some(auth_header) = get_aws_auth_header(req)? {
+        return Ok(auth_header);
+    }
+    if let Some(auth_header) = get_aws_auth_query(req)? {
+        return Ok(auth_header);
+    }
+    Err(Error::msg("request carries no SigV4 authorization"))
+}
+
+/// Parses an `X-Amz-Date` value (`%Y%m%dT%H%M%SZ`) into a UTC timestamp.
+fn parse_amz_date(amz_date: &str) -> Result<chrono::DateTime<Utc>, Error> {
+    Ok(
+        chrono::NaiveDateTime::parse_from_str(amz_date, "%Y%m%dT%H%M%SZ")
+            .map_err(|_| Error::msg("invalid X-Amz-Date"))?
+            .and_utc(),
+    )
+}
+
+/// Rejects requests whose `X-Amz-Date` is outside `max_skew` of now, or
+/// whose credential scope date doesn't match `X-Amz-Date`. Presigned
+/// (`AuthSource::Query`) requests skip the clock-skew check here since
+/// `presigned_url_expired` already enforces the right expiry semantics for
+/// them via `X-Amz-Expires`, which can be (and often is) longer than
+/// `max_skew`.
+fn check_request_time(auth_header: &AWSAuthHeader, max_skew: Duration) -> Result<(), Error> {
+    if auth_header.amz_date.len() < 8 {
+        return Err(Error::msg("missing or invalid X-Amz-Date"));
+    }
+    if auth_header.credential.date != auth_header.amz_date[..8] {
+        return Err(Error::msg(
+            "credential scope date does not match X-Amz-Date",
+        ));
+    }
+
+    if auth_header.source == AuthSource::Query {
+        return Ok(());
+    }
+
+    let signed_at = parse_amz_date(&auth_header.amz_date)?;
+    let skew = (Utc::now() - signed_at).num_seconds().unsigned_abs();
+    if skew > max_skew.as_secs() {
+        return Err(Error::msg("request time too skewed"));
+    }
+    Ok(())
+}
+
+/// Checks a presigned URL's `X-Amz-Date`/`X-Amz-Expires` pair against the
+/// current time, rejecting the request once it has expired.
+fn presigned_url_expired(auth_header: &AWSAuthHeader) -> Result<bool, Error> {
+    let Some(expires_secs) = auth_header.expires_secs else {
+        return Ok(false);
+    };
+    let signed_at = parse_amz_date(&auth_header.amz_date)?;
+    let age = Utc::now().signed_duration_since(signed_at).num_seconds();
+    Ok(age > expires_secs as i64)
+}
+
+/// Percent-encodes `s` per the AWS SigV4 rule set: unreserved characters
+/// (`ALPHA` / `DIGIT` / `-._~`) pass through untouched, everything else
+/// becomes an uppercase-hex `%XX` escape (space becomes `%20`, never `+`).
+/// When `encode_slash` is false, `/` is left unescaped, which is what AWS
+/// requires for canonicalizing path segments.
+fn uri_encode(s: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.as_bytes() {
+        match *byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(*byte as char)
             }
-            if item.starts_with("Signature=") {
-                auth_header.signature = item.trim_start_matches("Signature=").to_string();
+            b'/' if !encode_slash => out.push('/'),
+            _ => {
+                let _ = write!(out, "%{:02X}", byte);
             }
         }
     }
-    Ok(auth_header)
+    out
+}
+
+/// Builds the canonical (sorted, percent-encoded) query string per the
+/// SigV4 spec. `exclude` names a parameter that must be left out entirely
+/// (used to drop `X-Amz-Signature` from presigned-URL canonicalization).
+fn canonical_query_string(query: &str, exclude: Option<&str>) -> String {
+    let mut pairs: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .filter(|(k, _)| Some(k.as_str()) != exclude)
+        .map(|(k, v)| (uri_encode(&k, true), uri_encode(&v, true)))
+        .collect();
+    pairs.sort();
+    pairs
+        .into_iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Percent-encodes a request path, preserving `/` as a segment separator.
+fn canonical_uri_path(path: &str) -> String {
+    uri_encode(path, false)
 }
 
 fn get_canonical_request(
@@ -155,20 +338,33 @@ fn get_canonical_request(
     canonical_request.push('\n');
 
     // Add the path.
-    canonical_request.push_str(req.uri().path());
+    canonical_request.push_str(&canonical_uri_path(req.uri().path()));
     canonical_request.push('\n');
 
-    // Add the encoded query string.
+    // Add the sorted, percent-encoded query string. For presigned
+    // (query-string) auth, `X-Amz-Signature` itself must be excluded from
+    // what gets signed.
     let query_string = req.uri().query().unwrap_or_default();
-    canonical_request.push_str(query_string);
+    let exclude = (auth_header.source == AuthSource::Query).then_some("X-Amz-Signature");
+    canonical_request.push_str(&canonical_query_string(query_string, exclude));
     canonical_request.push('\n');
 
-    // Add headers to canonical request.
-    for header_name in &auth_header.signed_headers {
-        canonical_request.push_str(header_name);
+    // Add headers to canonical request: lowercase names, sorted, with
+    // internal whitespace runs in the value collapsed to a single space.
+    let mut signed_headers = auth_header.signed_headers.clone();
+    signed_headers.sort();
+    for header_name in &signed_headers {
+        let lower = header_name.to_lowercase();
+        canonical_request.push_str(&lower);
         canonical_request.push(':');
-        if let Some(val) = req.headers().get(header_name) {
-            canonical_request.push_str(val.to_str().unwrap_or(""));
+        if let Some(val) = req.headers().get(&lower) {
+            let collapsed = val
+                .to_str()
+                .unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ");
+            canonical_request.push_str(&collapsed);
         }
         canonical_request.push('\n');
     }
@@ -176,15 +372,26 @@ fn get_canonical_request(
     // Add newline separator.
     canonical_request.push('\n');
 
-    // Add signed headers names.
-    canonical_request.push_str(&auth_header.signed_headers.join(";"));
+    // Add signed headers names, sorted and lowercased.
+    canonical_request.push_str(
+        &signed_headers
+            .iter()
+            .map(|h| h.to_lowercase())
+            .collect::<Vec<String>>()
+            .join(";"),
+    );
     canonical_request.push('\n');
 
-    // Handle 'x-amz-content-sha256' header.
-    let sha_header = req.headers().get("x-amz-content-sha256").map_or_else(
-        || "UNSIGNED-PAYLOAD".to_string(),
-        |h| h.to_str().unwrap_or("UNSIGNED-PAYLOAD").to_owned(),
-    );
+    // Handle 'x-amz-content-sha256' header. Presigned URLs sign
+    // `UNSIGNED-PAYLOAD` regardless of what (if anything) the body contains.
+    let sha_header = if auth_header.source == AuthSource::Query {
+        "UNSIGNED-PAYLOAD".to_string()
+    } else {
+        req.headers().get("x-amz-content-sha256").map_or_else(
+            || "UNSIGNED-PAYLOAD".to_string(),
+            |h| h.to_str().unwrap_or("UNSIGNED-PAYLOAD").to_owned(),
+        )
+    };
     canonical_request.push_str(&sha_header);
     Ok(canonical_request)
 }
@@ -207,13 +414,13 @@ pub fn generate_sig_v4(
 ) -> Result<String, Error> {
     let canonical_request = get_canonical_request(&req, &parsed_auth_header)?;
     let string_to_sign = get_string_to_sign(
-        &req,
+        &parsed_auth_header.amz_date,
         &canonical_request,
         parsed_auth_header.credential.service.as_str(),
         parsed_auth_header.credential.region.as_str(),
     );
     let signing_key = get_signing_key(
-        &req,
+        &parsed_auth_header.amz_date,
         key_secret,
         parsed_auth_header.credential.region.as_str(),
         parsed_auth_header.credential.service.as_str(),
@@ -222,61 +429,367 @@ pub fn generate_sig_v4(
     Ok(signature)
 }
 
+/// Rebuilds the `Authorization` header from scratch for the upstream request,
+/// after the `Host` header (and possibly the signing region/service) changed.
+fn resign_for_upstream(
+    req: &Request<Body>,
+    auth_header: &AWSAuthHeader,
+    key_secret: &str,
+    upstream_region: &str,
+    upstream_service: &str,
+) -> Result<String, Error> {
+    let mut signed_headers = auth_header.signed_headers.clone();
+    if !signed_headers.iter().any(|h| h.eq_ignore_ascii_case("host")) {
+        signed_headers.push("host".to_string());
+    }
+
+    let upstream_auth_header = AWSAuthHeader {
+        credential: AWSAuthHeaderCredential {
+            key_id: auth_header.credential.key_id.clone(),
+            date: auth_header.credential.date.clone(),
+            region: upstream_region.to_string(),
+            service: upstream_service.to_string(),
+            request: auth_header.credential.request.clone(),
+        },
+        signed_headers,
+        signature: String::new(),
+        source: auth_header.source,
+        amz_date: auth_header.amz_date.clone(),
+        expires_secs: auth_header.expires_secs,
+    };
+
+    let signature = generate_sig_v4(req, &upstream_auth_header, key_secret)?;
+    let mut sorted_signed_headers = upstream_auth_header.signed_headers.clone();
+    sorted_signed_headers.sort();
+
+    Ok(format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}/{}/{}/aws4_request, SignedHeaders={}, Signature={}",
+        upstream_auth_header.credential.key_id,
+        upstream_auth_header.credential.date,
+        upstream_region,
+        upstream_service,
+        sorted_signed_headers
+            .iter()
+            .map(|h| h.to_lowercase())
+            .collect::<Vec<String>>()
+            .join(";"),
+        signature,
+    ))
+}
+
+fn credential_scope(auth_header: &AWSAuthHeader) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        &auth_header.amz_date[..8],
+        auth_header.credential.region,
+        auth_header.credential.service,
+        "aws4_request"
+    )
+}
+
+/// Verifies and strips AWS chunked transfer-encoding framing
+/// (`STREAMING-AWS4-HMAC-SHA256-PAYLOAD`) from a request body stream,
+/// yielding only the underlying chunk data, and only once each chunk's
+/// `chunk-signature` has been checked against the one derived from the
+/// previous chunk's signature. The first mismatch sets `failed` and ends
+/// the stream with an error so the caller can abort the proxy.
+struct ChunkedSigV4Stream<S> {
+    inner: S,
+    buffer: Vec<u8>,
+    prev_signature: String,
+    signing_key: Vec<u8>,
+    amz_date: String,
+    scope: String,
+    finished: bool,
+    failed: Arc<AtomicBool>,
+}
+
+impl<S> ChunkedSigV4Stream<S> {
+    fn new(
+        inner: S,
+        seed_signature: String,
+        signing_key: Vec<u8>,
+        amz_date: String,
+        scope: String,
+        failed: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            prev_signature: seed_signature,
+            signing_key,
+            amz_date,
+            scope,
+            finished: false,
+            failed,
+        }
+    }
+
+    /// Pulls one fully-buffered `<hex-size>;chunk-signature=<hex>\r\n<data>\r\n`
+    /// frame out of `self.buffer`, verifying it and advancing
+    /// `prev_signature`. Returns `Ok(None)` when the buffer doesn't yet hold
+    /// a complete frame.
+    fn try_take_chunk(&mut self) -> Result<Option<Bytes>, Error> {
+        let header_end = match self.buffer.windows(2).position(|w| w == b"\r\n") {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+        let header = std::str::from_utf8(&self.buffer[..header_end])
+            .map_err(|_| Error::msg("invalid chunk header"))?;
+        let (size_hex, sig_part) = header
+            .split_once(';')
+            .ok_or_else(|| Error::msg("malformed chunk header"))?;
+        let chunk_size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| Error::msg("invalid chunk size"))?;
+        let chunk_signature = sig_part.trim_start_matches("chunk-signature=");
+
+        let data_start = header_end + 2;
+        let data_end = data_start + chunk_size;
+        let frame_len = data_end + 2; // trailing \r\n
+        if self.buffer.len() < frame_len {
+            return Ok(None);
+        }
+
+        let chunk_data = self.buffer[data_start..data_end].to_vec();
+
+        let chunk_string_to_sign = format!(
+            "AWS4-HMAC-SHA256-PAYLOAD\n{}\n{}\n{}\n{}\n{}",
+            self.amz_date,
+            self.scope,
+            self.prev_signature,
+            hex::encode(get_sha256(b"")),
+            hex::encode(get_sha256(&chunk_data)),
+        );
+        let expected_signature =
+            hex::encode(get_hmac(&self.signing_key, chunk_string_to_sign.as_bytes()));
+
+        if !signatures_match(chunk_signature, &expected_signature) {
+            self.failed.store(true, Ordering::SeqCst);
+            return Err(Error::msg("chunk signature mismatch"));
+        }
+
+        self.prev_signature = expected_signature;
+        self.buffer.drain(..frame_len);
+
+        if chunk_size == 0 {
+            self.finished = true;
+        }
+
+        Ok(Some(Bytes::from(chunk_data)))
+    }
+}
+
+impl<S> Stream for ChunkedSigV4Stream<S>
+where
+    S: Stream<Item = Result<Bytes, axum::Error>> + Unpin,
+{
+    type Item = Result<Bytes, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.finished {
+                return Poll::Ready(None);
+            }
+
+            match self.try_take_chunk() {
+                Ok(Some(chunk)) => {
+                    if chunk.is_empty() {
+                        continue;
+                    }
+                    return Poll::Ready(Some(Ok(chunk)));
+                }
+                Ok(None) => {}
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => self.buffer.extend_from_slice(&bytes),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(Error::msg(e.to_string())))),
+                Poll::Ready(None) => {
+                    return if self.buffer.is_empty() {
+                        Poll::Ready(None)
+                    } else {
+                        Poll::Ready(Some(Err(Error::msg("stream ended mid-chunk"))))
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Compares a hex-encoded signature the client provided against the one we
+/// computed, in constant time so a mismatch can't be distinguished by timing
+/// (e.g. an early-exit byte-by-byte comparison would leak how many leading
+/// hex digits were guessed correctly).
+fn signatures_match(provided: &str, computed: &str) -> bool {
+    let (Ok(provided_bytes), Ok(computed_bytes)) = (hex::decode(provided), hex::decode(computed))
+    else {
+        return false;
+    };
+    provided_bytes.ct_eq(&computed_bytes).into()
+}
+
 #[axum::debug_handler]
 pub async fn proxy_request(
     State(state): State<AppState>,
     mut req: Request<Body>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let parsed_auth_header =
-        get_aws_auth_header(&req).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+) -> Result<impl IntoResponse, AppError> {
+    let parsed_auth_header = parse_auth(&req)
+        .map_err(|e| AppError::CustomCode(e, StatusCode::BAD_REQUEST))?;
+
+    if presigned_url_expired(&parsed_auth_header)
+        .map_err(|e| AppError::CustomCode(e, StatusCode::BAD_REQUEST))?
+    {
+        return Err(AppError::Forbidden("presigned URL has expired".to_string()));
+    }
+
+    check_request_time(&parsed_auth_header, state.max_clock_skew)
+        .map_err(|e| AppError::RequestTimeTooSkewed(e.to_string()))?;
+
     let provided_signature = parsed_auth_header.signature.clone();
 
-    // TODO: get the secret key
-    let key_secret = "hey";
-    let signature = generate_sig_v4(&req, &parsed_auth_header, key_secret)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    // Look up the credential, but don't let an unauthenticated caller learn
+    // anything about it (allowed regions/services, etc.) before they've
+    // proven possession of the secret key: everything up to and including
+    // the signature check must fail with the same generic response.
+    let credential = state
+        .credential_store
+        .lookup(&parsed_auth_header.credential.key_id)
+        .await
+        .ok_or_else(|| AppError::Forbidden("signature mismatch".to_string()))?;
 
-    println!("Provided Signature: {}", provided_signature);
-    println!("Calculated Signature: {}", signature);
+    let key_secret = credential.secret_key.as_str();
+    let signature = generate_sig_v4(&req, &parsed_auth_header, key_secret)
+        .map_err(|_| AppError::Forbidden("signature mismatch".to_string()))?;
 
-    if signature != signature {
-        return Err((StatusCode::BAD_REQUEST, "Signature mismatch".to_string()));
+    // Reject before any part of the body is read or proxied upstream, and
+    // before the allow-list checks below so they can't be used as an
+    // authorization oracle by someone who doesn't hold the secret key.
+    if !signatures_match(&provided_signature, &signature) {
+        return Err(AppError::Forbidden("signature mismatch".to_string()));
     }
 
-    // TODO: Look up new host
+    if !credential.allowed_regions.is_empty()
+        && !credential
+            .allowed_regions
+            .contains(&parsed_auth_header.credential.region)
+    {
+        return Err(AppError::Forbidden("region not permitted for this key".to_string()));
+    }
+    if !credential.allowed_services.is_empty()
+        && !credential
+            .allowed_services
+            .contains(&parsed_auth_header.credential.service)
+    {
+        return Err(AppError::Forbidden("service not permitted for this key".to_string()));
+    }
 
-    // Define the backend URL to proxy to.
-    let new_url = url::Url::parse(&"https://httpbin.org").unwrap();
+    // Route to the backend this access key id is configured for, preserving
+    // the original request's path and query — only the scheme/authority come
+    // from `backend_url`.
+    let backend_base = url::Url::parse(&credential.backend_url)
+        .map_err(|e| AppError::CustomCode(e.into(), StatusCode::BAD_GATEWAY))?;
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let new_url = backend_base
+        .join(path_and_query)
+        .map_err(|e| AppError::CustomCode(e.into(), StatusCode::BAD_GATEWAY))?;
 
-    // Clone headers and method before consuming the body.
+    // Rewrite Host to the upstream before recomputing anything against it.
     req.headers_mut().remove(axum::http::header::HOST);
     req.headers_mut()
         .insert(axum::http::header::HOST, new_url.host().unwrap().to_string().parse().unwrap());
     let method = req.method().clone();
-    let headers = req.headers().clone();
 
-    // Resign the request with the new host
-    let new_signature = generate_sig_v4(&req, &parsed_auth_header, key_secret)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
-
-    // Replace the signature in the header
-    let auth_header_value = req.headers().get("Authorization").unwrap();
-    let re = regex::Regex::new(r"Signature=[^,]+").unwrap();
-    let replacement = format!("Signature={}", new_signature);
-    let updated_auth_header = re
-        .replace_all(auth_header_value.to_str().unwrap(), replacement.as_str())
-        .to_string();
+    // Resign for the upstream: recompute the canonical request (with `host`
+    // signed) against the mutated headers, using the upstream's region and
+    // service if the credential routes there under a different scope.
+    let upstream_region = credential
+        .backend_region
+        .as_deref()
+        .unwrap_or(parsed_auth_header.credential.region.as_str());
+    let upstream_service = credential
+        .backend_service
+        .as_deref()
+        .unwrap_or(parsed_auth_header.credential.service.as_str());
+    let updated_auth_header = resign_for_upstream(
+        &req,
+        &parsed_auth_header,
+        key_secret,
+        upstream_region,
+        upstream_service,
+    )
+    .map_err(|e| AppError::CustomCode(e, StatusCode::BAD_REQUEST))?;
 
     req.headers_mut().remove(axum::http::header::AUTHORIZATION);
-    // Update the Authorization header in the request.
     req.headers_mut().insert(
         axum::http::header::AUTHORIZATION,
         updated_auth_header.parse().unwrap(),
     );
+    let headers = req.headers().clone();
+
+    // Convert the Axum body into a stream, verifying AWS chunked-transfer
+    // signatures along the way if the client declared a streaming payload.
+    let declared_payload_hash = headers
+        .get("x-amz-content-sha256")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let is_streaming_payload = declared_payload_hash.as_deref() == Some(STREAMING_PAYLOAD_SHA);
+    let verify_payload = credential.verify_payload
+        && !is_streaming_payload
+        && declared_payload_hash.as_deref().is_some_and(|h| h != "UNSIGNED-PAYLOAD");
+    let chunk_signature_failed = Arc::new(AtomicBool::new(false));
 
-    // Convert the Axum body into a stream and map its error type.
     let body_stream = req.into_body().into_data_stream();
-    let proxied_body = reqwest::Body::wrap_stream(body_stream.into_stream());
+    let proxied_body = if is_streaming_payload {
+        let signing_key = get_signing_key(
+            &parsed_auth_header.amz_date,
+            key_secret,
+            parsed_auth_header.credential.region.as_str(),
+            parsed_auth_header.credential.service.as_str(),
+        );
+        let chunked_stream = ChunkedSigV4Stream::new(
+            body_stream.into_stream(),
+            signature.clone(),
+            signing_key,
+            parsed_auth_header.amz_date.clone(),
+            credential_scope(&parsed_auth_header),
+            chunk_signature_failed.clone(),
+        );
+        reqwest::Body::wrap_stream(chunked_stream)
+    } else if verify_payload {
+        let declared_hash = declared_payload_hash.expect("checked above");
+        let mut body_bytes = Vec::new();
+        let mut stream = body_stream.into_stream();
+        while let Some(chunk) = stream
+            .try_next()
+            .await
+            .map_err(|e| AppError::CustomCode(anyhow::Error::msg(e.to_string()), StatusCode::BAD_REQUEST))?
+        {
+            if body_bytes.len() + chunk.len() > crate::MAX_BODY_BYTES {
+                return Err(AppError::CustomCode(
+                    anyhow::anyhow!("body too large to verify payload hash"),
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+            body_bytes.extend_from_slice(&chunk);
+        }
+
+        let computed_hash = hex::encode(get_sha256(&body_bytes));
+        if computed_hash != declared_hash {
+            return Err(AppError::CustomCode(
+                anyhow::anyhow!("payload hash mismatch"),
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        reqwest::Body::from(body_bytes)
+    } else {
+        reqwest::Body::wrap_stream(body_stream.into_stream())
+    };
 
     // Build the proxied request using the Reqwest client with streaming body.
     let client_req = state
@@ -285,10 +798,13 @@ pub async fn proxy_request(
         .headers(headers)
         .body(proxied_body);
 
-    let response = client_req
-        .send()
-        .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+    let response = client_req.send().await.map_err(|e| {
+        if chunk_signature_failed.load(Ordering::SeqCst) {
+            AppError::Forbidden("chunk signature mismatch".to_string())
+        } else {
+            AppError::CustomCode(e.into(), StatusCode::BAD_GATEWAY)
+        }
+    })?;
 
     // Build an Axum response from the Reqwest response.
     let status = response.status();
@@ -307,3 +823,270 @@ pub async fn proxy_request(
         .body(axum::body::Body::from_stream(stream))
         .unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{stream, StreamExt};
+
+    // https://docs.aws.amazon.com/IAM/latest/UserGuide/create-signed-request.html
+    fn iam_list_users_request() -> (Request<Body>, AWSAuthHeader) {
+        let req = Request::builder()
+            .method("GET")
+            .uri("/?Action=ListUsers&Version=2010-05-08")
+            .header(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )
+            .header("host", "iam.amazonaws.com")
+            .header("x-amz-date", "20150830T123600Z")
+            // The payload hash is taken from this header; the example's
+            // body is empty.
+            .header(
+                "x-amz-content-sha256",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        let auth_header = AWSAuthHeader {
+            credential: AWSAuthHeaderCredential {
+                key_id: "AKIDEXAMPLE".to_string(),
+                date: "20150830".to_string(),
+                region: "us-east-1".to_string(),
+                service: "iam".to_string(),
+                request: "aws4_request".to_string(),
+            },
+            signed_headers: vec![
+                "content-type".to_string(),
+                "host".to_string(),
+                "x-amz-date".to_string(),
+            ],
+            signature: String::new(),
+            source: AuthSource::Header,
+            amz_date: "20150830T123600Z".to_string(),
+            expires_secs: None,
+        };
+
+        (req, auth_header)
+    }
+
+    #[test]
+    fn canonical_request_matches_aws_iam_example() {
+        let (req, auth_header) = iam_list_users_request();
+        let canonical = get_canonical_request(&req, &auth_header).unwrap();
+        assert_eq!(
+            canonical,
+            "GET\n\
+             /\n\
+             Action=ListUsers&Version=2010-05-08\n\
+             content-type:application/x-www-form-urlencoded; charset=utf-8\n\
+             host:iam.amazonaws.com\n\
+             x-amz-date:20150830T123600Z\n\
+             \n\
+             content-type;host;x-amz-date\n\
+             e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn uri_encode_preserves_unreserved_and_escapes_rest() {
+        assert_eq!(uri_encode("abc123-._~", true), "abc123-._~");
+        assert_eq!(uri_encode("a b", true), "a%20b");
+        assert_eq!(uri_encode("a/b", true), "a%2Fb");
+        assert_eq!(uri_encode("a/b", false), "a/b");
+    }
+
+    #[test]
+    fn canonical_query_string_sorts_and_encodes() {
+        let query = "Version=2010-05-08&Action=ListUsers";
+        assert_eq!(
+            canonical_query_string(query, None),
+            "Action=ListUsers&Version=2010-05-08"
+        );
+    }
+
+    #[test]
+    fn canonical_query_string_excludes_named_param() {
+        let query = "X-Amz-Signature=deadbeef&Action=ListUsers";
+        assert_eq!(
+            canonical_query_string(query, Some("X-Amz-Signature")),
+            "Action=ListUsers"
+        );
+    }
+
+    fn test_auth_header(source: AuthSource, date: &str, amz_date: &str) -> AWSAuthHeader {
+        AWSAuthHeader {
+            credential: AWSAuthHeaderCredential {
+                key_id: "AKIDEXAMPLE".to_string(),
+                date: date.to_string(),
+                region: "us-east-1".to_string(),
+                service: "iam".to_string(),
+                request: "aws4_request".to_string(),
+            },
+            signed_headers: Vec::new(),
+            signature: String::new(),
+            source,
+            amz_date: amz_date.to_string(),
+            expires_secs: None,
+        }
+    }
+
+    #[test]
+    fn check_request_time_enforces_skew_for_header_source() {
+        let auth_header = test_auth_header(AuthSource::Header, "20150830", "20150830T123600Z");
+        let err = check_request_time(&auth_header, Duration::from_secs(15 * 60)).unwrap_err();
+        assert_eq!(err.to_string(), "request time too skewed");
+    }
+
+    #[test]
+    fn check_request_time_skips_skew_check_for_presigned_query_requests() {
+        // Far outside max_skew, but presigned requests rely on
+        // `presigned_url_expired`/`X-Amz-Expires` instead of this check.
+        let auth_header = test_auth_header(AuthSource::Query, "20150830", "20150830T123600Z");
+        check_request_time(&auth_header, Duration::from_secs(15 * 60)).unwrap();
+    }
+
+    #[test]
+    fn check_request_time_rejects_short_amz_date() {
+        let auth_header = test_auth_header(AuthSource::Header, "20150830", "x");
+        assert!(check_request_time(&auth_header, Duration::from_secs(15 * 60)).is_err());
+    }
+
+    #[test]
+    fn check_request_time_rejects_credential_scope_date_mismatch() {
+        let auth_header = test_auth_header(AuthSource::Header, "20200101", "20150830T123600Z");
+        assert!(check_request_time(&auth_header, Duration::from_secs(15 * 60)).is_err());
+    }
+
+    fn build_chunk_frame(
+        signing_key: &[u8],
+        amz_date: &str,
+        scope: &str,
+        prev_signature: &str,
+        chunk_data: &[u8],
+    ) -> (Vec<u8>, String) {
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256-PAYLOAD\n{}\n{}\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            prev_signature,
+            hex::encode(get_sha256(b"")),
+            hex::encode(get_sha256(chunk_data)),
+        );
+        let signature = hex::encode(get_hmac(signing_key, string_to_sign.as_bytes()));
+
+        let mut frame = format!("{:x};chunk-signature={}\r\n", chunk_data.len(), signature)
+            .into_bytes();
+        frame.extend_from_slice(chunk_data);
+        frame.extend_from_slice(b"\r\n");
+        (frame, signature)
+    }
+
+    async fn collect_chunks<S>(mut stream: ChunkedSigV4Stream<S>) -> Result<Vec<u8>, Error>
+    where
+        S: Stream<Item = Result<Bytes, axum::Error>> + Unpin,
+    {
+        let mut out = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            out.extend_from_slice(&chunk?);
+        }
+        Ok(out)
+    }
+
+    #[tokio::test]
+    async fn chunked_stream_reassembles_chunks_split_across_polls() {
+        let signing_key = b"test-signing-key".to_vec();
+        let amz_date = "20150830T123600Z".to_string();
+        let scope = "20150830/us-east-1/s3/aws4_request".to_string();
+        let seed_signature = "seed0000".repeat(8);
+
+        let (frame1, sig1) =
+            build_chunk_frame(&signing_key, &amz_date, &scope, &seed_signature, b"hello ");
+        let (frame2, sig2) = build_chunk_frame(&signing_key, &amz_date, &scope, &sig1, b"world");
+        let (frame3, _) = build_chunk_frame(&signing_key, &amz_date, &scope, &sig2, b"");
+
+        let mut all = frame1.clone();
+        all.extend_from_slice(&frame2);
+        all.extend_from_slice(&frame3);
+        // Split mid-frame to exercise buffering across multiple inner polls.
+        let split_at = frame1.len() + 3;
+        let (part1, part2) = all.split_at(split_at);
+
+        let inner = stream::iter(vec![
+            Ok::<Bytes, axum::Error>(Bytes::copy_from_slice(part1)),
+            Ok(Bytes::copy_from_slice(part2)),
+        ]);
+
+        let failed = Arc::new(AtomicBool::new(false));
+        let chunked = ChunkedSigV4Stream::new(
+            inner,
+            seed_signature,
+            signing_key,
+            amz_date,
+            scope,
+            failed.clone(),
+        );
+
+        let body = collect_chunks(chunked).await.unwrap();
+        assert_eq!(body, b"hello world");
+        assert!(!failed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn chunked_stream_rejects_tampered_chunk_signature() {
+        let signing_key = b"test-signing-key".to_vec();
+        let amz_date = "20150830T123600Z".to_string();
+        let scope = "20150830/us-east-1/s3/aws4_request".to_string();
+        let seed_signature = "seed0000".repeat(8);
+
+        let (mut frame, _) =
+            build_chunk_frame(&signing_key, &amz_date, &scope, &seed_signature, b"hello");
+        let sig_pos = frame
+            .windows(16)
+            .position(|w| w == b"chunk-signature=")
+            .unwrap()
+            + 16;
+        frame[sig_pos] = if frame[sig_pos] == b'a' { b'b' } else { b'a' };
+
+        let inner = stream::iter(vec![Ok::<Bytes, axum::Error>(Bytes::from(frame))]);
+        let failed = Arc::new(AtomicBool::new(false));
+        let chunked = ChunkedSigV4Stream::new(
+            inner,
+            seed_signature,
+            signing_key,
+            amz_date,
+            scope,
+            failed.clone(),
+        );
+
+        assert!(collect_chunks(chunked).await.is_err());
+        assert!(failed.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn chunked_stream_ends_on_zero_length_terminal_chunk() {
+        let signing_key = b"test-signing-key".to_vec();
+        let amz_date = "20150830T123600Z".to_string();
+        let scope = "20150830/us-east-1/s3/aws4_request".to_string();
+        let seed_signature = "seed0000".repeat(8);
+
+        let (frame, _) =
+            build_chunk_frame(&signing_key, &amz_date, &scope, &seed_signature, b"");
+
+        let inner = stream::iter(vec![Ok::<Bytes, axum::Error>(Bytes::from(frame))]);
+        let failed = Arc::new(AtomicBool::new(false));
+        let chunked = ChunkedSigV4Stream::new(
+            inner,
+            seed_signature,
+            signing_key,
+            amz_date,
+            scope,
+            failed.clone(),
+        );
+
+        let body = collect_chunks(chunked).await.unwrap();
+        assert!(body.is_empty());
+        assert!(!failed.load(Ordering::SeqCst));
+    }
+}